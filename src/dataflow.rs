@@ -34,6 +34,7 @@ impl Dataflow {
         Self(self.0.clone_ref(py))
     }
 
+    #[tracing::instrument(level = "debug", skip(self, py))]
     pub(crate) fn substeps(&self, py: Python) -> PyResult<Vec<Operator>> {
         self.0.getattr(py, "substeps")?.extract(py)
     }
@@ -81,7 +82,16 @@ impl Operator {
         self.0.getattr(py, "step_id")?.extract(py)
     }
 
+    /// This is walked recursively while building a dataflow, visiting
+    /// every operator once, so a `debug` span here
+    /// (keyed on this operator's own `step_id`) mirrors the operator
+    /// graph in the trace output the same way it appears in the
+    /// dataflow itself.
+    #[tracing::instrument(level = "debug", skip(self, py), fields(step_id = tracing::field::Empty))]
     pub(crate) fn substeps(&self, py: Python) -> PyResult<Vec<Operator>> {
+        if let Ok(step_id) = self.step_id(py) {
+            tracing::Span::current().record("step_id", tracing::field::display(&step_id));
+        }
         self.0.getattr(py, "substeps")?.extract(py)
     }
 
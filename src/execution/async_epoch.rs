@@ -0,0 +1,197 @@
+use std::fmt::Debug;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::Arc;
+use std::task::{Context, Poll};
+use std::time::{Duration, Instant};
+
+use timely::dataflow::operators::generic::builder_rc::OperatorBuilder;
+use timely::dataflow::ProbeHandle;
+use timely::dataflow::Scope;
+use timely::dataflow::Stream;
+use timely::Data;
+
+use crate::inputs::AsyncInputReader;
+use crate::recovery::StepId;
+use crate::recovery::{StateKey, StateUpdateStream};
+
+use super::epoch_close::{close_epoch_update, epoch_close_span};
+use super::retry::{ReaderError, RetryState};
+use super::waker::waker_for;
+use super::EpochConfig;
+
+/// The in-flight `reader.next()` future for an async input source.
+///
+/// There's never more than one outstanding at a time: we start a new
+/// one only once the previous one resolves.
+enum NextFuture<D> {
+    Idle,
+    Polling(Pin<Box<dyn Future<Output = Result<Option<D>, ReaderError>>>>),
+}
+
+/// Input source with the same epoch/snapshot/backoff logic as
+/// [`super::periodic_epoch::periodic_epoch_source`], but backed by an
+/// async [`AsyncInputReader`] driven by a shared multi-thread Tokio
+/// runtime created once per worker, instead of a synchronous polling
+/// one.
+///
+/// The timely operator closure itself stays synchronous: each
+/// activation either starts the reader's future or polls the one
+/// already in flight, using a [`std::task::Waker`] wrapping the
+/// operator's `Activator` so a `Poll::Pending` parks us until the
+/// future is actually ready rather than busy-looping. While parked
+/// the operator is not re-activated, so an epoch whose
+/// `epoch_length` has elapsed is only closed once the waker fires.
+pub(crate) fn async_epoch_source<S, D>(
+    scope: &S,
+    step_id: StepId,
+    state_key: StateKey,
+    mut reader: Box<dyn AsyncInputReader<D>>,
+    runtime: tokio::runtime::Handle,
+    start_at: S::Timestamp,
+    probe: &ProbeHandle<S::Timestamp>,
+    epoch_length: Duration,
+    base_delay: Duration,
+    max_delay: Duration,
+    max_retries: u32,
+) -> (Stream<S, D>, StateUpdateStream<S>)
+where
+    S: Scope<Timestamp = u64>,
+    D: Data + Debug + 'static,
+{
+    let mut op_builder = OperatorBuilder::new(format!("{step_id}"), scope.clone());
+
+    let (mut output_wrapper, output_stream) = op_builder.new_output();
+    let (mut state_update_wrapper, state_update_stream) = op_builder.new_output();
+
+    let probe = probe.clone();
+    let info = op_builder.operator_info();
+    let activator = Arc::new(scope.activator_for(&info.address[..]));
+    let waker = waker_for(activator.clone());
+
+    // One span for the whole lifetime of this operator, so every
+    // event and child span below it can be filtered down to just this
+    // source.
+    let op_span = tracing::info_span!("epoch_source", step_id = %step_id);
+
+    op_builder.build(move |mut init_caps| {
+        let mut state_update_cap = init_caps.pop().map(|cap| cap.delayed(&start_at));
+        let mut output_cap = init_caps.pop().map(|cap| cap.delayed(&start_at));
+
+        let mut eof = false;
+        let mut epoch_started = Instant::now();
+        let mut items_this_epoch: u64 = 0;
+        let mut retry_state = RetryState::new(base_delay, max_delay, max_retries);
+        let mut next_future: NextFuture<D> = NextFuture::Idle;
+
+        move |_input_frontiers| {
+            let _enter = op_span.enter();
+
+            if let (Some(output_cap), Some(state_update_cap)) =
+                (output_cap.as_mut(), state_update_cap.as_mut())
+            {
+                assert!(output_cap.time() == state_update_cap.time());
+                let epoch = output_cap.time();
+
+                if !probe.less_than(epoch) {
+                    if epoch_started.elapsed() > epoch_length {
+                        // Snapshot just before incrementing epoch to
+                        // get the "end of the epoch state".
+                        let snapshot_started = Instant::now();
+                        let snapshot = reader.snapshot();
+                        let snapshot_took = snapshot_started.elapsed();
+
+                        let update = close_epoch_update(
+                            step_id.clone(),
+                            state_key.clone(),
+                            *epoch,
+                            snapshot,
+                        );
+                        state_update_wrapper
+                            .activate()
+                            .session(&state_update_cap)
+                            .give(update);
+
+                        let _epoch_span = epoch_close_span(
+                            *epoch,
+                            epoch_started.elapsed(),
+                            items_this_epoch,
+                            snapshot_took,
+                        )
+                        .entered();
+
+                        let next_epoch = epoch + 1;
+
+                        output_cap.downgrade(&next_epoch);
+                        state_update_cap.downgrade(&next_epoch);
+
+                        epoch_started = Instant::now();
+                        items_this_epoch = 0;
+                    }
+
+                    let now = Instant::now();
+                    if !retry_state.is_waiting(now) {
+                        if matches!(next_future, NextFuture::Idle) {
+                            // `reader.next()` may build its future out
+                            // of Tokio resources (timers, sockets), so
+                            // it needs to be constructed with the
+                            // runtime entered.
+                            let _guard = runtime.enter();
+                            next_future = NextFuture::Polling(reader.next());
+                        }
+
+                        if let NextFuture::Polling(fut) = &mut next_future {
+                            let mut cx = Context::from_waker(&waker);
+                            match fut.as_mut().poll(&mut cx) {
+                                Poll::Pending => {
+                                    // Leave the future in flight; the
+                                    // waker will re-activate us when
+                                    // it's ready.
+                                }
+                                Poll::Ready(result) => {
+                                    next_future = NextFuture::Idle;
+                                    match result {
+                                        Ok(None) => {
+                                            eof = true;
+                                        }
+                                        Ok(Some(item)) => {
+                                            retry_state.record_success();
+                                            items_this_epoch += 1;
+                                            output_wrapper
+                                                .activate()
+                                                .session(&output_cap)
+                                                .give(item);
+                                        }
+                                        Err(ReaderError::Transient(_msg)) => {
+                                            if retry_state.record_failure(now) {
+                                                // Exceeded max_retries;
+                                                // treat as permanent,
+                                                // same as EOF.
+                                                eof = true;
+                                            }
+                                        }
+                                        Err(ReaderError::Permanent(_msg)) => {
+                                            eof = true;
+                                        }
+                                    }
+                                }
+                            }
+                        }
+                    }
+                }
+            }
+
+            if eof {
+                output_cap = None;
+                state_update_cap = None;
+            } else if !matches!(next_future, NextFuture::Polling(_)) {
+                // Only keep re-arming eagerly while we're not parked
+                // on an in-flight future; once one is outstanding the
+                // waker is responsible for reactivating us.
+                activator.activate();
+            }
+        }
+    });
+
+    (output_stream, state_update_stream)
+}
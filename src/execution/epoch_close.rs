@@ -0,0 +1,49 @@
+use std::time::Duration;
+
+use tracing::Span;
+
+use crate::recovery::{State, StateBytes, StateOp, StateRecoveryKey, StateUpdate};
+use crate::recovery::{StateKey, StepId};
+
+/// Build the `StateUpdate` an epoch source gives to its
+/// `state_update_stream` when it closes an epoch.
+///
+/// Pulled out so `periodic_epoch_source`, `event_time_epoch_source`,
+/// and `async_epoch_source` don't each carry their own copy of this
+/// boilerplate; only how `snapshot` itself gets computed differs
+/// between them.
+pub(crate) fn close_epoch_update(
+    step_id: StepId,
+    state_key: StateKey,
+    epoch: u64,
+    snapshot: StateBytes,
+) -> StateUpdate {
+    let recovery_key = StateRecoveryKey {
+        step_id,
+        state_key,
+        epoch,
+    };
+    let op = StateOp::Upsert(State {
+        snapshot,
+        next_awake: None,
+    });
+    StateUpdate(recovery_key, op)
+}
+
+/// The `"epoch_close"` span all three epoch sources enter with
+/// identical fields when they close an epoch. Short-lived: it only
+/// exists to carry the stats for this one epoch close.
+pub(crate) fn epoch_close_span(
+    epoch: u64,
+    elapsed: Duration,
+    items: u64,
+    snapshot_took: Duration,
+) -> Span {
+    tracing::info_span!(
+        "epoch_close",
+        epoch = epoch,
+        elapsed_ms = elapsed.as_secs_f64() * 1000.0,
+        items = items,
+        snapshot_ms = snapshot_took.as_secs_f64() * 1000.0,
+    )
+}
@@ -0,0 +1,473 @@
+use std::task::Poll;
+use std::time::{Duration, Instant};
+
+use chrono::{DateTime, Utc};
+use pyo3::exceptions::PyValueError;
+use pyo3::prelude::*;
+use pyo3::PyResult;
+use timely::dataflow::operators::generic::builder_rc::OperatorBuilder;
+use timely::dataflow::ProbeHandle;
+use timely::dataflow::Scope;
+use timely::dataflow::Stream;
+
+use crate::inputs::InputReader;
+use crate::recovery::{StateBytes, StepId};
+use crate::recovery::{StateKey, StateUpdateStream};
+
+use super::conversion::{parse_field_spec, Conversion, ConvertingInputReader};
+use super::epoch_close::{close_epoch_update, epoch_close_span};
+use super::periodic_epoch::{DEFAULT_BASE_DELAY, DEFAULT_MAX_DELAY, DEFAULT_MAX_RETRIES};
+use super::retry::{ReaderError, RetryState};
+use super::EpochConfig;
+
+/// Increment epochs based on the event time carried in each item
+/// rather than system time.
+///
+/// Each item's event time is recovered by calling `dt_getter` on it,
+/// then bucketed into `epoch_length`-wide windows counted from
+/// `origin`. A watermark, the latest event time seen minus
+/// `max_lateness`, is used to decide when an epoch is fully closed;
+/// items whose event time falls below the watermark are dropped as
+/// late, even if their epoch has not closed yet.
+///
+/// Args:
+///
+///   dt_getter: Python callable that takes an item and returns the
+///       `datetime.datetime` it occurred at.
+///
+///   epoch_length (datetime.timedelta): Event time length of each
+///       epoch.
+///
+///   max_lateness (datetime.timedelta): Amount of time an item's
+///       event time is allowed to trail the latest event time seen
+///       before it is dropped.
+///
+///   base_delay (datetime.timedelta): Starting backoff delay after a
+///       transient input error. Doubles on each consecutive failure.
+///       Defaults to 50ms.
+///
+///   max_delay (datetime.timedelta): Cap on the backoff delay.
+///       Defaults to 10 seconds.
+///
+///   max_retries (int): Number of consecutive transient failures to
+///       tolerate before treating the error as permanent and ending
+///       the input. Defaults to 10.
+///
+///   field_conversions (List[str]): `"field_name:conversion_spec"`
+///       tokens applied to raw byte-source items before `dt_getter`
+///       sees them, e.g. `"occurred_at:timestamp_fmt:%Y-%m-%d %H:%M:%S"`.
+///       Defaults to no conversions.
+///
+/// Returns:
+///
+///   Config object. Pass this as the `epoch_config` parameter of
+///   your execution entry point.
+#[pyclass(module="bytewax.window", extends=EpochConfig)]
+#[pyo3(
+    text_signature = "(dt_getter, epoch_length, max_lateness, base_delay, max_delay, max_retries, field_conversions)"
+)]
+pub(crate) struct EventTimeEpochConfig {
+    #[pyo3(get)]
+    pub(crate) dt_getter: PyObject,
+    #[pyo3(get)]
+    pub(crate) epoch_length: chrono::Duration,
+    #[pyo3(get)]
+    pub(crate) max_lateness: chrono::Duration,
+    #[pyo3(get)]
+    pub(crate) base_delay: chrono::Duration,
+    #[pyo3(get)]
+    pub(crate) max_delay: chrono::Duration,
+    #[pyo3(get)]
+    pub(crate) max_retries: u32,
+    /// `"field_name:conversion_spec"` tokens, e.g.
+    /// `"occurred_at:timestamp_fmt:%Y-%m-%d %H:%M:%S"`. Applied to
+    /// raw byte-source items by a `ConvertingInputReader` before they
+    /// reach this config's `dt_getter`, so a converted timestamp
+    /// field can be used directly as the event-time key.
+    #[pyo3(get)]
+    pub(crate) field_conversions: Vec<String>,
+}
+
+#[pymethods]
+impl EventTimeEpochConfig {
+    #[new]
+    #[args(
+        dt_getter,
+        epoch_length,
+        max_lateness,
+        base_delay = "chrono::Duration::from_std(DEFAULT_BASE_DELAY).unwrap()",
+        max_delay = "chrono::Duration::from_std(DEFAULT_MAX_DELAY).unwrap()",
+        max_retries = "DEFAULT_MAX_RETRIES",
+        field_conversions = "Vec::new()"
+    )]
+    pub(crate) fn new(
+        dt_getter: PyObject,
+        epoch_length: chrono::Duration,
+        max_lateness: chrono::Duration,
+        base_delay: chrono::Duration,
+        max_delay: chrono::Duration,
+        max_retries: u32,
+        field_conversions: Vec<String>,
+    ) -> PyResult<(Self, EpochConfig)> {
+        // Validate eagerly so a typo in a conversion spec surfaces at
+        // dataflow-construction time, not the first time an item
+        // flows through.
+        for spec in &field_conversions {
+            parse_field_spec(spec)?;
+        }
+        Ok((
+            Self {
+                dt_getter,
+                epoch_length,
+                max_lateness,
+                base_delay,
+                max_delay,
+                max_retries,
+                field_conversions,
+            },
+            EpochConfig {},
+        ))
+    }
+
+    /// Pickle as a tuple.
+    #[allow(clippy::type_complexity)]
+    fn __getstate__(
+        &self,
+        py: Python,
+    ) -> (
+        &str,
+        PyObject,
+        chrono::Duration,
+        chrono::Duration,
+        chrono::Duration,
+        chrono::Duration,
+        u32,
+        Vec<String>,
+    ) {
+        (
+            "EventTimeEpochConfig",
+            self.dt_getter.clone_ref(py),
+            self.epoch_length,
+            self.max_lateness,
+            self.base_delay,
+            self.max_delay,
+            self.max_retries,
+            self.field_conversions.clone(),
+        )
+    }
+
+    /// Egregious hack see [`SqliteRecoveryConfig::__getnewargs__`].
+    #[allow(clippy::type_complexity)]
+    fn __getnewargs__(
+        &self,
+        py: Python,
+    ) -> (
+        PyObject,
+        chrono::Duration,
+        chrono::Duration,
+        chrono::Duration,
+        chrono::Duration,
+        u32,
+        Vec<String>,
+    ) {
+        (
+            py.None(),
+            chrono::Duration::zero(),
+            chrono::Duration::zero(),
+            chrono::Duration::zero(),
+            chrono::Duration::zero(),
+            0,
+            Vec::new(),
+        )
+    }
+
+    /// Unpickle from tuple of arguments.
+    fn __setstate__(&mut self, state: &PyAny) -> PyResult<()> {
+        if let Ok((
+            "EventTimeEpochConfig",
+            dt_getter,
+            epoch_length,
+            max_lateness,
+            base_delay,
+            max_delay,
+            max_retries,
+            field_conversions,
+        )) = state.extract()
+        {
+            self.dt_getter = dt_getter;
+            self.epoch_length = epoch_length;
+            self.max_lateness = max_lateness;
+            self.base_delay = base_delay;
+            self.max_delay = max_delay;
+            self.max_retries = max_retries;
+            self.field_conversions = field_conversions;
+            Ok(())
+        } else {
+            Err(PyValueError::new_err(format!(
+                "bad pickle contents for EventTimeEpochConfig: {state:?}"
+            )))
+        }
+    }
+}
+
+impl EventTimeEpochConfig {
+    /// Parse `field_conversions` into the form
+    /// [`event_time_epoch_source`] actually wants. Re-parses on every
+    /// call rather than caching, since this only runs once per
+    /// dataflow build, not per item.
+    pub(crate) fn parsed_field_conversions(&self) -> PyResult<Vec<(String, Conversion)>> {
+        self.field_conversions
+            .iter()
+            .map(|spec| parse_field_spec(spec))
+            .collect()
+    }
+}
+
+/// Round a duration since `origin` down to the epoch it belongs in.
+///
+/// Event times before `origin` are clamped into epoch `0` rather than
+/// going negative.
+fn epoch_of(origin: DateTime<Utc>, epoch_length: chrono::Duration, dt: DateTime<Utc>) -> u64 {
+    let since_origin = dt - origin;
+    if since_origin < chrono::Duration::zero() {
+        0
+    } else {
+        let since_origin_ns = since_origin.num_nanoseconds().unwrap_or(i64::MAX);
+        let epoch_length_ns = epoch_length.num_nanoseconds().unwrap_or(1).max(1);
+        (since_origin_ns / epoch_length_ns) as u64
+    }
+}
+
+/// Call `dt_getter` on `item` and extract the `datetime.datetime` it
+/// returns.
+///
+/// Surfaces the `PyErr` instead of panicking: `dt_getter` can raise on
+/// a malformed item, and even a `datetime.datetime` it successfully
+/// returns can fail to extract as `DateTime<Utc>` if it's naive
+/// (tz-less). Both are user data errors, not reasons to crash the
+/// whole worker.
+fn event_time(py: Python, dt_getter: &PyObject, item: &PyObject) -> PyResult<DateTime<Utc>> {
+    dt_getter.call1(py, (item,))?.extract(py)
+}
+
+/// Input source that increments the epoch from event time carried in
+/// the data, watermarked against out-of-order arrival.
+///
+/// Items are always Python objects here, not a generic `D`: both
+/// `dt_getter` and `field_conversions` only make sense against a
+/// Python value, and when `field_conversions` is non-empty `reader`
+/// is wrapped in a [`ConvertingInputReader`] so every item already has
+/// its fields converted by the time `dt_getter` sees it.
+pub(crate) fn event_time_epoch_source<S>(
+    scope: &S,
+    step_id: StepId,
+    state_key: StateKey,
+    reader: Box<dyn InputReader<PyObject>>,
+    start_at: S::Timestamp,
+    probe: &ProbeHandle<S::Timestamp>,
+    dt_getter: PyObject,
+    epoch_length: chrono::Duration,
+    max_lateness: chrono::Duration,
+    origin: DateTime<Utc>,
+    base_delay: Duration,
+    max_delay: Duration,
+    max_retries: u32,
+    field_conversions: Vec<(String, Conversion)>,
+) -> (Stream<S, PyObject>, StateUpdateStream<S>)
+where
+    S: Scope<Timestamp = u64>,
+{
+    let mut reader = reader;
+    if !field_conversions.is_empty() {
+        reader = Box::new(ConvertingInputReader::new(reader, field_conversions));
+    }
+
+    let mut op_builder = OperatorBuilder::new(format!("{step_id}"), scope.clone());
+
+    let (mut output_wrapper, output_stream) = op_builder.new_output();
+    let (mut state_update_wrapper, state_update_stream) = op_builder.new_output();
+
+    let probe = probe.clone();
+    let info = op_builder.operator_info();
+    let activator = scope.activator_for(&info.address[..]);
+
+    // One span for the whole lifetime of this operator, so every
+    // event and child span below it can be filtered down to just this
+    // source.
+    let op_span = tracing::info_span!("epoch_source", step_id = %step_id);
+
+    op_builder.build(move |mut init_caps| {
+        let mut state_update_cap = init_caps.pop().map(|cap| cap.delayed(&start_at));
+        let mut output_cap = init_caps.pop().map(|cap| cap.delayed(&start_at));
+
+        let mut eof = false;
+        // The latest event time seen starts at `origin`, so until
+        // we've actually seen an event only items more than
+        // `max_lateness` before `origin` are late.
+        let mut watermark = origin;
+        let mut epoch_started = Instant::now();
+        let mut items_this_epoch: u64 = 0;
+        let mut retry_state = RetryState::new(base_delay, max_delay, max_retries);
+
+        move |_input_frontiers| {
+            let _enter = op_span.enter();
+
+            if let (Some(output_cap), Some(state_update_cap)) =
+                (output_cap.as_mut(), state_update_cap.as_mut())
+            {
+                assert!(output_cap.time() == state_update_cap.time());
+                let epoch = *output_cap.time();
+
+                if !probe.less_than(&epoch) {
+                    let now = Instant::now();
+                    if !retry_state.is_waiting(now) {
+                        match reader.next() {
+                            Poll::Pending => {}
+                            Poll::Ready(Ok(None)) => {
+                                // Flush: act as if the watermark has
+                                // advanced to the end of time so every
+                                // remaining epoch is closed before we
+                                // drop the capabilities.
+                                watermark = DateTime::<Utc>::MAX_UTC;
+                                eof = true;
+                            }
+                            Poll::Ready(Ok(Some(item))) => {
+                                retry_state.record_success();
+                                let dt = Python::with_gil(|py| event_time(py, &dt_getter, &item));
+
+                                match dt {
+                                    Err(err) => {
+                                        // A bad item or a `dt_getter`
+                                        // that raised/returned a naive
+                                        // datetime is a data problem,
+                                        // not a reason to take down
+                                        // the whole worker. Log and
+                                        // drop, the same as a late
+                                        // item.
+                                        tracing::error!(error = %err, "dt_getter failed; dropping item");
+                                    }
+                                    Ok(dt) if dt < watermark - max_lateness => {
+                                        // Late; it trails the latest event
+                                        // time seen by more than
+                                        // `max_lateness`. Drop it.
+                                    }
+                                    Ok(dt) => {
+                                        watermark = watermark.max(dt);
+                                        items_this_epoch += 1;
+                                        // Capabilities only ever downgrade, so
+                                        // an item can never be given behind
+                                        // the epoch we're currently holding.
+                                        let item_epoch =
+                                            epoch_of(origin, epoch_length, dt).max(epoch);
+                                        output_wrapper
+                                            .activate()
+                                            .session(&output_cap.delayed(&item_epoch))
+                                            .give(item);
+                                    }
+                                }
+                            }
+                            Poll::Ready(Err(ReaderError::Transient(_msg))) => {
+                                if retry_state.record_failure(now) {
+                                    // Exceeded max_retries; treat as
+                                    // permanent, same as EOF.
+                                    watermark = DateTime::<Utc>::MAX_UTC;
+                                    eof = true;
+                                }
+                            }
+                            Poll::Ready(Err(ReaderError::Permanent(_msg))) => {
+                                watermark = DateTime::<Utc>::MAX_UTC;
+                                eof = true;
+                            }
+                        }
+                    }
+
+                    let closed_through =
+                        epoch_of(origin, epoch_length, watermark - max_lateness).max(epoch);
+                    if closed_through > epoch {
+                        // Snapshot just before incrementing epoch to
+                        // get the "end of the epoch state".
+                        let snapshot_started = Instant::now();
+                        let snapshot = reader.snapshot();
+                        let snapshot_took = snapshot_started.elapsed();
+
+                        let update = close_epoch_update(
+                            step_id.clone(),
+                            state_key.clone(),
+                            epoch,
+                            StateBytes::ser::<(StateBytes, DateTime<Utc>, DateTime<Utc>)>(&(
+                                snapshot, watermark, origin,
+                            )),
+                        );
+                        state_update_wrapper
+                            .activate()
+                            .session(&state_update_cap)
+                            .give(update);
+
+                        let _epoch_span = epoch_close_span(
+                            epoch,
+                            epoch_started.elapsed(),
+                            items_this_epoch,
+                            snapshot_took,
+                        )
+                        .entered();
+
+                        output_cap.downgrade(&closed_through);
+                        state_update_cap.downgrade(&closed_through);
+
+                        epoch_started = Instant::now();
+                        items_this_epoch = 0;
+                    }
+                }
+            }
+
+            if eof {
+                output_cap = None;
+                state_update_cap = None;
+            } else {
+                activator.activate();
+            }
+        }
+    });
+
+    (output_stream, state_update_stream)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn dt(s: &str) -> DateTime<Utc> {
+        s.parse().unwrap()
+    }
+
+    #[test]
+    fn epoch_of_buckets_by_epoch_length() {
+        let origin = dt("2023-01-01T00:00:00Z");
+        let epoch_length = chrono::Duration::seconds(10);
+
+        assert_eq!(epoch_of(origin, epoch_length, origin), 0);
+        assert_eq!(
+            epoch_of(origin, epoch_length, dt("2023-01-01T00:00:09Z")),
+            0
+        );
+        assert_eq!(
+            epoch_of(origin, epoch_length, dt("2023-01-01T00:00:10Z")),
+            1
+        );
+        assert_eq!(
+            epoch_of(origin, epoch_length, dt("2023-01-01T00:01:05Z")),
+            6
+        );
+    }
+
+    #[test]
+    fn epoch_of_clamps_before_origin_to_zero() {
+        let origin = dt("2023-01-01T00:00:00Z");
+        let epoch_length = chrono::Duration::seconds(10);
+
+        assert_eq!(
+            epoch_of(origin, epoch_length, dt("2022-12-31T23:59:00Z")),
+            0
+        );
+    }
+}
@@ -0,0 +1,119 @@
+use crate::recovery::StateUpdate;
+
+/// A flat, region-allocated container of [`StateUpdate`]s.
+///
+/// Intended for a source with a large keyspace that produces many
+/// `StateUpdate`s per epoch close, so they can be pushed into one
+/// contiguous backing `Vec<u8>` and drained once instead of `give`n
+/// one at a time. `periodic_epoch_source`, `event_time_epoch_source`,
+/// and `async_epoch_source` each only produce a single `StateUpdate`
+/// per epoch close, though, so there's nothing for this to batch for
+/// them yet — routing that single update through `push`/`iter` would
+/// just be an extra bincode serialize/deserialize round trip per
+/// close for no benefit, so none of the three wire it in. This stays
+/// unused in production until an operator actually has many per-epoch
+/// updates to amortize; see the tests below for its round-trip
+/// behavior in the meantime.
+///
+/// This is deliberately a thin stand-in for timely's
+/// `Columnation`/`FlatStack` containers, not a real `timely::Container`
+/// swapped in as `StateUpdateStream`'s backing store — that would need
+/// `Container`/`PushContainer` impls and a cross-cutting change to
+/// `StateUpdateStream`'s definition in `crate::recovery`.
+#[derive(Default)]
+pub(crate) struct FlatStateUpdates {
+    // Bincode-serialized `StateUpdate`s, back to back.
+    region: Vec<u8>,
+    // `(start, end)` byte ranges into `region`, one per pushed update.
+    offsets: Vec<(usize, usize)>,
+}
+
+/// A borrowed view of one [`StateUpdate`] living inside a
+/// [`FlatStateUpdates`] arena.
+pub(crate) struct ReadItem<'a> {
+    bytes: &'a [u8],
+}
+
+impl<'a> ReadItem<'a> {
+    /// Deserialize the referenced bytes back into an owned
+    /// [`StateUpdate`]. Call sites that only need to forward the
+    /// bytes on to a writer can skip this and use
+    /// [`ReadItem::as_bytes`] instead.
+    pub(crate) fn deserialize(&self) -> bincode::Result<StateUpdate> {
+        bincode::deserialize(self.bytes)
+    }
+
+    pub(crate) fn as_bytes(&self) -> &'a [u8] {
+        self.bytes
+    }
+}
+
+impl FlatStateUpdates {
+    /// Copy `update`'s bytes into the arena. Matches the shape of
+    /// `timely`'s `Container::push`; this type does not implement
+    /// `Container`, so it cannot back a stream a `Session` gives into.
+    pub(crate) fn push(&mut self, update: StateUpdate) {
+        let start = self.region.len();
+        // `StateUpdate` is already `Serialize` for recovery
+        // round-tripping through pickle/bincode elsewhere, so reuse
+        // that instead of inventing a second encoding.
+        bincode::serialize_into(&mut self.region, &update)
+            .expect("StateUpdate must be bincode-serializable");
+        let end = self.region.len();
+        self.offsets.push((start, end));
+    }
+
+    pub(crate) fn clear(&mut self) {
+        self.region.clear();
+        self.offsets.clear();
+    }
+
+    pub(crate) fn len(&self) -> usize {
+        self.offsets.len()
+    }
+
+    pub(crate) fn is_empty(&self) -> bool {
+        self.offsets.is_empty()
+    }
+
+    pub(crate) fn iter(&self) -> impl Iterator<Item = ReadItem<'_>> {
+        self.offsets.iter().map(move |&(start, end)| ReadItem {
+            bytes: &self.region[start..end],
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use pyo3::prelude::*;
+
+    use super::*;
+    use crate::execution::epoch_close::close_epoch_update;
+    use crate::recovery::{StateBytes, StateKey, StepId};
+
+    #[test]
+    fn push_then_iter_round_trips_the_bytes_a_push_put_in() {
+        pyo3::prepare_freethreaded_python();
+        Python::with_gil(|py| {
+            let step_id: StepId = "test_step".into_py(py).extract(py).unwrap();
+            let state_key: StateKey = "test_key".into_py(py).extract(py).unwrap();
+            let update = close_epoch_update(step_id, state_key, 0, StateBytes::ser::<()>(&()));
+            let expected_bytes = bincode::serialize(&update).unwrap();
+
+            let mut buf = FlatStateUpdates::default();
+            assert!(buf.is_empty());
+            buf.push(update);
+            assert_eq!(buf.len(), 1);
+
+            let items: Vec<_> = buf.iter().collect();
+            assert_eq!(items.len(), 1);
+            assert_eq!(items[0].as_bytes(), expected_bytes.as_slice());
+            items[0]
+                .deserialize()
+                .expect("must round-trip a StateUpdate");
+
+            buf.clear();
+            assert!(buf.is_empty());
+        });
+    }
+}
@@ -12,12 +12,20 @@ use timely::dataflow::Stream;
 use timely::Data;
 
 use crate::inputs::InputReader;
-use crate::recovery::{State, StateOp, StepId};
-use crate::recovery::{StateKey, StateUpdate};
-use crate::recovery::{StateRecoveryKey, StateUpdateStream};
+use crate::recovery::StepId;
+use crate::recovery::{StateKey, StateUpdateStream};
 
+use super::epoch_close::{close_epoch_update, epoch_close_span};
+use super::retry::{ReaderError, RetryState};
 use super::EpochConfig;
 
+/// Default backoff parameters used when a config doesn't override
+/// them. 50ms doubling up to 10s, tolerating 10 consecutive failures
+/// and giving up on the 11th.
+pub(crate) const DEFAULT_BASE_DELAY: Duration = Duration::from_millis(50);
+pub(crate) const DEFAULT_MAX_DELAY: Duration = Duration::from_secs(10);
+pub(crate) const DEFAULT_MAX_RETRIES: u32 = 10;
+
 /// Increment epochs at regular system time intervals.
 ///
 /// This is the default with 10 second epoch intervals if no
@@ -28,39 +36,98 @@ use super::EpochConfig;
 ///   epoch_length (datetime.timedelta): System time length of each
 ///       epoch.
 ///
+///   base_delay (datetime.timedelta): Starting backoff delay after a
+///       transient input error. Doubles on each consecutive failure.
+///       Defaults to 50ms.
+///
+///   max_delay (datetime.timedelta): Cap on the backoff delay.
+///       Defaults to 10 seconds.
+///
+///   max_retries (int): Number of consecutive transient failures to
+///       tolerate before treating the error as permanent and ending
+///       the input. Defaults to 10.
+///
 /// Returns:
 ///
 ///   Config object. Pass this as the `epoch_config` parameter of
 ///   your execution entry point.
 #[pyclass(module="bytewax.window", extends=EpochConfig)]
-#[pyo3(text_signature = "(epoch_length)")]
+#[pyo3(text_signature = "(epoch_length, base_delay, max_delay, max_retries)")]
 pub(crate) struct PeriodicEpochConfig {
     #[pyo3(get)]
     pub(crate) epoch_length: chrono::Duration,
+    #[pyo3(get)]
+    pub(crate) base_delay: chrono::Duration,
+    #[pyo3(get)]
+    pub(crate) max_delay: chrono::Duration,
+    #[pyo3(get)]
+    pub(crate) max_retries: u32,
 }
 
 #[pymethods]
 impl PeriodicEpochConfig {
     #[new]
-    #[args(epoch_length)]
-    pub(crate) fn new(epoch_length: chrono::Duration) -> (Self, EpochConfig) {
-        (Self { epoch_length }, EpochConfig {})
+    #[args(
+        epoch_length,
+        base_delay = "chrono::Duration::from_std(DEFAULT_BASE_DELAY).unwrap()",
+        max_delay = "chrono::Duration::from_std(DEFAULT_MAX_DELAY).unwrap()",
+        max_retries = "DEFAULT_MAX_RETRIES"
+    )]
+    pub(crate) fn new(
+        epoch_length: chrono::Duration,
+        base_delay: chrono::Duration,
+        max_delay: chrono::Duration,
+        max_retries: u32,
+    ) -> (Self, EpochConfig) {
+        (
+            Self {
+                epoch_length,
+                base_delay,
+                max_delay,
+                max_retries,
+            },
+            EpochConfig {},
+        )
     }
 
     /// Pickle as a tuple.
-    fn __getstate__(&self) -> (&str, chrono::Duration) {
-        ("PeriodicEpochConfig", self.epoch_length)
+    fn __getstate__(
+        &self,
+    ) -> (
+        &str,
+        chrono::Duration,
+        chrono::Duration,
+        chrono::Duration,
+        u32,
+    ) {
+        (
+            "PeriodicEpochConfig",
+            self.epoch_length,
+            self.base_delay,
+            self.max_delay,
+            self.max_retries,
+        )
     }
 
     /// Egregious hack see [`SqliteRecoveryConfig::__getnewargs__`].
-    fn __getnewargs__(&self) -> (chrono::Duration,) {
-        (chrono::Duration::zero(),)
+    fn __getnewargs__(&self) -> (chrono::Duration, chrono::Duration, chrono::Duration, u32) {
+        (
+            chrono::Duration::zero(),
+            chrono::Duration::zero(),
+            chrono::Duration::zero(),
+            0,
+        )
     }
 
     /// Unpickle from tuple of arguments.
     fn __setstate__(&mut self, state: &PyAny) -> PyResult<()> {
-        if let Ok(("PeriodicEpochConfig", epoch_length)) = state.extract() {
+        if let Ok(("PeriodicEpochConfig", epoch_length, base_delay, max_delay, max_retries)) =
+            state.extract()
+        {
             self.epoch_length = epoch_length;
+            self.base_delay = base_delay;
+            self.max_delay = max_delay;
+            self.max_retries = max_retries;
             Ok(())
         } else {
             Err(PyValueError::new_err(format!(
@@ -80,6 +147,9 @@ pub(crate) fn periodic_epoch_source<S, D>(
     start_at: S::Timestamp,
     probe: &ProbeHandle<S::Timestamp>,
     epoch_length: Duration,
+    base_delay: Duration,
+    max_delay: Duration,
+    max_retries: u32,
 ) -> (Stream<S, D>, StateUpdateStream<S>)
 where
     S: Scope<Timestamp = u64>,
@@ -94,14 +164,23 @@ where
     let info = op_builder.operator_info();
     let activator = scope.activator_for(&info.address[..]);
 
+    // One span for the whole lifetime of this operator, so every
+    // event and child span below it can be filtered down to just this
+    // source.
+    let op_span = tracing::info_span!("epoch_source", step_id = %step_id);
+
     op_builder.build(move |mut init_caps| {
         let mut state_update_cap = init_caps.pop().map(|cap| cap.delayed(&start_at));
         let mut output_cap = init_caps.pop().map(|cap| cap.delayed(&start_at));
 
         let mut eof = false;
         let mut epoch_started = Instant::now();
+        let mut items_this_epoch: u64 = 0;
+        let mut retry_state = RetryState::new(base_delay, max_delay, max_retries);
 
         move |_input_frontiers| {
+            let _enter = op_span.enter();
+
             if let (Some(output_cap), Some(state_update_cap)) =
                 (output_cap.as_mut(), state_update_cap.as_mut())
             {
@@ -112,37 +191,60 @@ where
                     if epoch_started.elapsed() > epoch_length {
                         // Snapshot just before incrementing epoch to
                         // get the "end of the epoch state".
+                        let snapshot_started = Instant::now();
                         let snapshot = reader.snapshot();
-                        let recovery_key = StateRecoveryKey {
-                            step_id: step_id.clone(),
-                            state_key: state_key.clone(),
-                            epoch: epoch.clone(),
-                        };
-                        let op = StateOp::Upsert(State {
+                        let snapshot_took = snapshot_started.elapsed();
+
+                        let update = close_epoch_update(
+                            step_id.clone(),
+                            state_key.clone(),
+                            *epoch,
                             snapshot,
-                            next_awake: None,
-                        });
-                        let update = StateUpdate(recovery_key, op);
+                        );
                         state_update_wrapper
                             .activate()
                             .session(&state_update_cap)
                             .give(update);
 
+                        let _epoch_span = epoch_close_span(
+                            *epoch,
+                            epoch_started.elapsed(),
+                            items_this_epoch,
+                            snapshot_took,
+                        )
+                        .entered();
+
                         let next_epoch = epoch + 1;
 
                         output_cap.downgrade(&next_epoch);
                         state_update_cap.downgrade(&next_epoch);
 
                         epoch_started = Instant::now();
+                        items_this_epoch = 0;
                     }
 
-                    match reader.next() {
-                        Poll::Pending => {}
-                        Poll::Ready(None) => {
-                            eof = true;
-                        }
-                        Poll::Ready(Some(item)) => {
-                            output_wrapper.activate().session(&output_cap).give(item);
+                    let now = Instant::now();
+                    if !retry_state.is_waiting(now) {
+                        match reader.next() {
+                            Poll::Pending => {}
+                            Poll::Ready(Ok(None)) => {
+                                eof = true;
+                            }
+                            Poll::Ready(Ok(Some(item))) => {
+                                retry_state.record_success();
+                                items_this_epoch += 1;
+                                output_wrapper.activate().session(&output_cap).give(item);
+                            }
+                            Poll::Ready(Err(ReaderError::Transient(_msg))) => {
+                                if retry_state.record_failure(now) {
+                                    // Exceeded max_retries; treat as
+                                    // permanent, same as EOF.
+                                    eof = true;
+                                }
+                            }
+                            Poll::Ready(Err(ReaderError::Permanent(_msg))) => {
+                                eof = true;
+                            }
                         }
                     }
                 }
@@ -152,6 +254,8 @@ where
                 output_cap = None;
                 state_update_cap = None;
             } else {
+                // Keep re-arming even while backing off so epoch
+                // generation doesn't stall.
                 activator.activate();
             }
         }
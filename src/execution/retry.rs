@@ -0,0 +1,133 @@
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::time::{Duration, Instant};
+
+/// An error surfaced by an [`crate::inputs::InputReader`] while
+/// polling for the next item.
+///
+/// `Transient` errors are retried with exponential backoff by the
+/// source operator; `Permanent` errors end the input, exactly like
+/// reaching end-of-stream.
+#[derive(Debug)]
+pub(crate) enum ReaderError {
+    Transient(String),
+    Permanent(String),
+}
+
+static JITTER_SEED: AtomicU32 = AtomicU32::new(0);
+
+/// Up to 249 milliseconds of jitter so that many workers backing
+/// off at once don't all retry in lockstep. Doesn't need to be
+/// cryptographically random, so a tiny LCG is enough and avoids
+/// pulling in a dependency just for this.
+fn jitter() -> Duration {
+    let seed = JITTER_SEED.fetch_add(1, Ordering::Relaxed);
+    let x = seed.wrapping_mul(1_103_515_245).wrapping_add(12_345);
+    Duration::from_millis((x % 250) as u64)
+}
+
+/// Exponential backoff bookkeeping for a single input source.
+///
+/// Call [`RetryState::record_failure`] whenever `reader.next()`
+/// surfaces a [`ReaderError::Transient`], and check
+/// [`RetryState::is_waiting`] before polling the reader again. A
+/// successful poll should call [`RetryState::record_success`] to
+/// reset the backoff.
+pub(crate) struct RetryState {
+    base_delay: Duration,
+    max_delay: Duration,
+    max_retries: u32,
+    consecutive_failures: u32,
+    retry_after: Option<Instant>,
+}
+
+impl RetryState {
+    pub(crate) fn new(base_delay: Duration, max_delay: Duration, max_retries: u32) -> Self {
+        Self {
+            base_delay,
+            max_delay,
+            max_retries,
+            consecutive_failures: 0,
+            retry_after: None,
+        }
+    }
+
+    /// `true` if we're still inside a backoff window and shouldn't
+    /// poll the reader yet. The caller should keep re-arming its
+    /// activator while this is `true` so epoch generation doesn't
+    /// stall.
+    pub(crate) fn is_waiting(&self, now: Instant) -> bool {
+        self.retry_after
+            .map_or(false, |retry_after| now < retry_after)
+    }
+
+    pub(crate) fn record_success(&mut self) {
+        self.consecutive_failures = 0;
+        self.retry_after = None;
+    }
+
+    /// Schedule the next retry after a transient failure. Returns
+    /// `true` once `max_retries` has been exceeded, at which point
+    /// the caller should treat the error as permanent instead.
+    pub(crate) fn record_failure(&mut self, now: Instant) -> bool {
+        self.consecutive_failures += 1;
+        if self.consecutive_failures > self.max_retries {
+            return true;
+        }
+
+        // The first failure waits `base_delay`, the next twice that, ...
+        let exp = 2u32.saturating_pow((self.consecutive_failures - 1).min(31));
+        let delay = self.base_delay.saturating_mul(exp).min(self.max_delay);
+        self.retry_after = Some(now + delay + jitter());
+        false
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn record_failure_backs_off_exponentially_until_max_delay() {
+        let mut retry_state = RetryState::new(
+            Duration::from_millis(10),
+            Duration::from_millis(100),
+            u32::MAX,
+        );
+        let now = Instant::now();
+
+        assert!(!retry_state.record_failure(now));
+        assert!(retry_state.is_waiting(now));
+        assert!(!retry_state.is_waiting(now + Duration::from_secs(1)));
+
+        // Keep failing until the delay saturates at max_delay.
+        for _ in 0..10 {
+            retry_state.record_failure(now);
+        }
+        let retry_after = retry_state.retry_after.unwrap();
+        assert!(retry_after <= now + Duration::from_millis(100) + Duration::from_millis(250));
+    }
+
+    #[test]
+    fn record_failure_gives_up_after_max_retries() {
+        let mut retry_state =
+            RetryState::new(Duration::from_millis(10), Duration::from_millis(100), 2);
+        let now = Instant::now();
+
+        assert!(!retry_state.record_failure(now));
+        assert!(!retry_state.record_failure(now));
+        assert!(retry_state.record_failure(now));
+    }
+
+    #[test]
+    fn record_success_resets_backoff() {
+        let mut retry_state =
+            RetryState::new(Duration::from_millis(10), Duration::from_millis(100), 5);
+        let now = Instant::now();
+
+        retry_state.record_failure(now);
+        assert!(retry_state.is_waiting(now));
+
+        retry_state.record_success();
+        assert!(!retry_state.is_waiting(now));
+    }
+}
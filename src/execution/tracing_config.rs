@@ -0,0 +1,99 @@
+use pyo3::exceptions::PyValueError;
+use pyo3::prelude::*;
+use pyo3::PyResult;
+use tracing::Level;
+use tracing_subscriber::fmt::format::FmtSpan;
+
+/// Which `tracing` subscriber format to install.
+///
+/// Args:
+///
+///   log_level (str): One of `"error"`, `"warn"`, `"info"`, `"debug"`,
+///       `"trace"`. Defaults to `"info"`.
+///
+///   json (bool): Emit JSON lines to stderr for machine parsing
+///       instead of the compact human-readable format. Defaults to
+///       `False`.
+///
+/// Returns:
+///
+///   Config object. Pass this as the `tracing_config` parameter of
+///   your execution entry point.
+#[pyclass(module = "bytewax.tracing")]
+#[pyo3(text_signature = "(log_level, json)")]
+pub(crate) struct TracingConfig {
+    #[pyo3(get)]
+    pub(crate) log_level: String,
+    #[pyo3(get)]
+    pub(crate) json: bool,
+}
+
+#[pymethods]
+impl TracingConfig {
+    #[new]
+    #[args(log_level = "\"info\".to_string()", json = "false")]
+    pub(crate) fn new(log_level: String, json: bool) -> Self {
+        Self { log_level, json }
+    }
+
+    /// Pickle as a tuple.
+    fn __getstate__(&self) -> (&str, String, bool) {
+        ("TracingConfig", self.log_level.clone(), self.json)
+    }
+
+    /// Egregious hack see [`SqliteRecoveryConfig::__getnewargs__`].
+    fn __getnewargs__(&self) -> (String, bool) {
+        ("info".to_string(), false)
+    }
+
+    /// Unpickle from tuple of arguments.
+    fn __setstate__(&mut self, state: &PyAny) -> PyResult<()> {
+        if let Ok(("TracingConfig", log_level, json)) = state.extract() {
+            self.log_level = log_level;
+            self.json = json;
+            Ok(())
+        } else {
+            Err(PyValueError::new_err(format!(
+                "bad pickle contents for TracingConfig: {state:?}"
+            )))
+        }
+    }
+}
+
+fn parse_level(log_level: &str) -> PyResult<Level> {
+    log_level.parse().map_err(|_| {
+        PyValueError::new_err(format!(
+            "unknown log_level {log_level:?}, must be one of \
+             \"error\", \"warn\", \"info\", \"debug\", \"trace\""
+        ))
+    })
+}
+
+/// Install a global `tracing` subscriber per `config` for the
+/// lifetime of the process.
+///
+/// Called once from the execution entry point before the dataflow is
+/// built, so the `epoch_source` spans and the dataflow-construction
+/// spans in [`crate::dataflow`] are all captured from the start.
+pub(crate) fn init_tracing(config: &TracingConfig) -> PyResult<()> {
+    let level = parse_level(&config.log_level)?;
+
+    let subscriber = tracing_subscriber::fmt()
+        .with_max_level(level)
+        .with_span_events(FmtSpan::CLOSE);
+
+    let result = if config.json {
+        subscriber.json().try_init()
+    } else {
+        subscriber.compact().try_init()
+    };
+
+    // A subscriber may already be installed (e.g. in tests that set
+    // one up themselves); that's not worth failing the whole
+    // execution entry point over.
+    if let Err(err) = result {
+        eprintln!("tracing subscriber already installed: {err}");
+    }
+
+    Ok(())
+}
@@ -0,0 +1,40 @@
+use std::sync::Arc;
+use std::task::{RawWaker, RawWakerVTable, Waker};
+
+use timely::scheduling::Activator;
+
+/// Build a [`std::task::Waker`] backed by a timely [`Activator`].
+///
+/// Waking it re-activates the operator the `Activator` was created
+/// for, so an [`crate::inputs::AsyncInputReader`] future can park
+/// itself on `Poll::Pending` and have the timely scheduler poll it
+/// again once it's actually ready, instead of the operator
+/// busy-looping on `activator.activate()`.
+pub(crate) fn waker_for(activator: Arc<Activator>) -> Waker {
+    fn clone(ptr: *const ()) -> RawWaker {
+        let arc = unsafe { Arc::from_raw(ptr as *const Activator) };
+        let cloned = arc.clone();
+        std::mem::forget(arc);
+        RawWaker::new(Arc::into_raw(cloned) as *const (), &VTABLE)
+    }
+
+    fn wake(ptr: *const ()) {
+        let arc = unsafe { Arc::from_raw(ptr as *const Activator) };
+        arc.activate();
+    }
+
+    fn wake_by_ref(ptr: *const ()) {
+        let arc = unsafe { Arc::from_raw(ptr as *const Activator) };
+        arc.activate();
+        std::mem::forget(arc);
+    }
+
+    fn drop_waker(ptr: *const ()) {
+        unsafe { drop(Arc::from_raw(ptr as *const Activator)) };
+    }
+
+    static VTABLE: RawWakerVTable = RawWakerVTable::new(clone, wake, wake_by_ref, drop_waker);
+
+    let raw = RawWaker::new(Arc::into_raw(activator) as *const (), &VTABLE);
+    unsafe { Waker::from_raw(raw) }
+}